@@ -1,18 +1,59 @@
+use clap::Parser;
 use std::fs;
+use std::path::PathBuf;
+
+/// Convert a Day One JSON export into an Org time tree.
+#[derive(Parser, Debug)]
+#[clap(name = "do2org", about = "Convert a Day One journal export to Org")]
+pub struct Args {
+    /// Path to the Day One `Journal.json` export.
+    #[clap(long, default_value = "Journal.json")]
+    pub input: PathBuf,
+
+    /// Directory to write the Org output into (defaults to stdout).
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+
+    /// Parse and validate the journal but write nothing.
+    #[clap(long)]
+    pub dry_run: bool,
+
+    /// Log per-entry progress and warnings.
+    #[clap(long, short)]
+    pub verbose: bool,
+
+    /// When writing to a directory, split files by year or by month.
+    #[clap(long, value_enum, default_value = "year")]
+    pub split: time_tree::Split,
+
+    /// Output format when printing to stdout.
+    #[clap(long, value_enum, default_value = "org")]
+    pub format: time_tree::Format,
+
+    /// Reverse-geocode locations with missing place names over HTTP.
+    #[clap(long)]
+    pub geocode: bool,
+}
 
 pub mod day_one {
     use chrono::prelude::*;
     use lazy_static::lazy_static;
     use regex::Regex;
-    use serde::Deserialize;
+    use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag};
+    use serde::{Deserialize, Serialize};
     use std::collections::HashMap;
-    use std::io::Write;
-    use std::process::{Command, Stdio};
+
+    /// Headings are shifted down by this many levels, matching the old
+    /// `pandoc --shift-heading-level-by=4` invocation.
+    const HEADING_SHIFT: usize = 4;
 
     lazy_static! {
-        static ref PHOTO_REGEX: Regex = Regex::new(r"\[\[dayone-moment://[^\]]+\]\]").unwrap();
-        static ref MARKDOWN_PHOTO_REGEX: Regex =
-            Regex::new(r"!\[\]\(dayone-moment://[^)]+\)").unwrap();
+        // Matches a `dayone-moment://` reference in either the `[[…]]` or
+        // `![](…)` spelling and captures the moment identifier so photos,
+        // videos and audio can be resolved by identity rather than order.
+        static ref MOMENT_REGEX: Regex =
+            Regex::new(r"(?:\[\[|!\[\]\()dayone-moment:/*(?:[a-z]+/)?([0-9A-Fa-f]+)(?:\]\]|\))")
+                .unwrap();
         static ref MARKDOWN_HEADING_REGEX: Regex = Regex::new(r"^#+\s").unwrap();
     }
 
@@ -21,20 +62,20 @@ pub mod day_one {
         pub version: String,
     }
 
-    #[derive(Deserialize, Debug)]
+    #[derive(Deserialize, Serialize, Debug)]
     #[serde(rename_all = "camelCase")]
     pub struct Weather {
         pub conditions_description: Option<String>,
         pub moon_phase_code: Option<String>,
     }
 
-    #[derive(Deserialize, Debug)]
+    #[derive(Deserialize, Serialize, Debug)]
     pub struct Music {
         pub artist: String,
         pub track: String,
     }
 
-    #[derive(Deserialize, Debug)]
+    #[derive(Deserialize, Serialize, Debug)]
     #[serde(rename_all = "camelCase")]
     pub struct Location {
         pub longitude: f32,
@@ -44,7 +85,7 @@ pub mod day_one {
 
     mod dates {
         use chrono::prelude::*;
-        use serde::{Deserialize, Deserializer};
+        use serde::{Deserialize, Deserializer, Serializer};
         const FORMAT: &'static str = "%Y-%m-%dT%H:%M:%SZ";
 
         pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
@@ -55,23 +96,63 @@ pub mod day_one {
             Utc.datetime_from_str(&s, FORMAT)
                 .map_err(serde::de::Error::custom)
         }
+
+        pub fn serialize<S>(date: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_str(&date.format(FORMAT).to_string())
+        }
+    }
+
+    /// The three kinds of attachment a Day One moment can be.
+    #[derive(Debug, Clone, Copy)]
+    pub enum MediaKind {
+        Photo,
+        Video,
+        Audio,
     }
 
-    #[derive(Deserialize, Debug, Clone)]
+    impl MediaKind {
+        /// Folder name inside the Day One export.
+        pub fn source_dir(&self) -> &'static str {
+            match self {
+                MediaKind::Photo => "photos",
+                MediaKind::Video => "videos",
+                MediaKind::Audio => "audio",
+            }
+        }
+
+        /// Folder name created in the Org output tree.
+        pub fn output_dir(&self) -> &'static str {
+            match self {
+                MediaKind::Photo => "images",
+                MediaKind::Video => "videos",
+                MediaKind::Audio => "audio",
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone)]
     #[serde(rename_all = "camelCase")]
     pub struct Photo {
         pub md5: String,
         pub r#type: String,
+        pub identifier: String,
         pub order_in_entry: u8,
     }
 
     impl Photo {
-        pub fn link(&self) -> String {
-            format!["[[./images/{}.{}]]", self.md5, self.r#type]
+        pub fn file_name(&self) -> String {
+            format!("{}.{}", self.md5, self.r#type)
+        }
+
+        pub fn link(&self, kind: MediaKind) -> String {
+            format!["[[./{}/{}]]", kind.output_dir(), self.file_name()]
         }
     }
 
-    #[derive(Deserialize, Debug)]
+    #[derive(Deserialize, Serialize, Debug)]
     #[serde(rename_all = "camelCase")]
     pub struct Entry {
         #[serde(with = "dates")]
@@ -81,6 +162,8 @@ pub mod day_one {
         pub weather: Option<Weather>,
         pub music: Option<Music>,
         pub photos: Option<Vec<Photo>>,
+        pub videos: Option<Vec<Photo>>,
+        pub audios: Option<Vec<Photo>>,
     }
 
     fn get_moon(moon: &str) -> String {
@@ -111,7 +194,33 @@ pub mod day_one {
             self.creation_date.day()
         }
 
-        pub fn properties(&self) -> HashMap<String, String> {
+        /// Every attachment on this entry paired with its [`MediaKind`].
+        pub fn moments(&self) -> Vec<(MediaKind, &Photo)> {
+            let mut out = Vec::new();
+            for (kind, list) in [
+                (MediaKind::Photo, &self.photos),
+                (MediaKind::Video, &self.videos),
+                (MediaKind::Audio, &self.audios),
+            ] {
+                if let Some(list) = list {
+                    for moment in list {
+                        out.push((kind, moment));
+                    }
+                }
+            }
+            out
+        }
+
+        /// Map from moment identifier to its Org link, used to substitute
+        /// `dayone-moment://` placeholders by identity.
+        pub fn moment_links(&self) -> HashMap<String, String> {
+            self.moments()
+                .into_iter()
+                .map(|(kind, moment)| (moment.identifier.clone(), moment.link(kind)))
+                .collect()
+        }
+
+        pub fn properties(&self, geocoder: Option<&crate::geocode::Geocoder>) -> HashMap<String, String> {
             let mut props = HashMap::default();
 
             if let Some(weather) = &self.weather {
@@ -133,62 +242,138 @@ pub mod day_one {
             if let Some(location) = &self.location {
                 props.insert("Latitude".to_string(), format!("{}", location.latitude));
                 props.insert("Longitude".to_string(), format!("{}", location.longitude));
-                props.insert("Location".to_string(), location.place_name.to_string());
+                props.insert(
+                    "Geo".to_string(),
+                    format!("geo:{},{}", location.latitude, location.longitude),
+                );
+                props.insert(
+                    "Map".to_string(),
+                    format!(
+                        "[[https://www.openstreetmap.org/#map=16/{}/{}][OpenStreetMap]]",
+                        location.latitude, location.longitude
+                    ),
+                );
+
+                let mut name = location.place_name.to_string();
+                if name.is_empty() {
+                    if let Some(geocoder) = geocoder {
+                        if let Some(resolved) = geocoder.reverse(location.latitude, location.longitude) {
+                            name = resolved;
+                        }
+                    }
+                }
+                props.insert("Location".to_string(), name);
             }
 
             props
         }
 
-        pub fn title(&self, first_photo_link: Option<String>) -> Option<String> {
+        pub fn title(&self, links: &HashMap<String, String>) -> Option<String> {
             if let Some(text) = &self.text {
                 if let Some(line) = text.lines().next() {
-                    let line = MARKDOWN_HEADING_REGEX.replace(&line, "").to_string();
-                    let line = match first_photo_link {
-                        Some(first_photo_link) => MARKDOWN_PHOTO_REGEX
-                            .replace(&line, first_photo_link.as_str())
-                            .to_string(),
-                        None => line,
-                    };
-                    return Some(line.to_string());
+                    let line = MARKDOWN_HEADING_REGEX.replace(line, "").to_string();
+                    return Some(substitute_moments(&line, links));
                 }
             }
             None
         }
 
-        pub fn body(&self, photos: &Option<Vec<Photo>>) -> Option<String> {
+        pub fn body(&self, links: &HashMap<String, String>) -> Option<String> {
             if let Some(text) = &self.text {
-                let mut pandoc = Command::new("pandoc")
-                    .args(&["-f", "markdown", "-t", "org", "--shift-heading-level-by=4"])
-                    .stdin(Stdio::piped())
-                    .stdout(Stdio::piped())
-                    .spawn()
-                    .expect("panda dog couldn't do it");
-                {
-                    let stdin = pandoc
-                        .stdin
-                        .as_mut()
-                        .expect("couldn't open stdin for panda dog");
-                    stdin
-                        .write_all(text.as_bytes())
-                        .expect("couldn't feed the panda dog");
+                let body = markdown_to_org(text, HEADING_SHIFT);
+                return Some(substitute_moments(&body, links));
+            }
+            None
+        }
+    }
+
+    /// Replace each `dayone-moment://` placeholder with the link for the
+    /// matching moment identifier, warning when no attachment matches.
+    fn substitute_moments(text: &str, links: &HashMap<String, String>) -> String {
+        MOMENT_REGEX
+            .replace_all(text, |caps: &regex::Captures| {
+                let id = &caps[1];
+                match links.get(id) {
+                    Some(link) => link.clone(),
+                    None => {
+                        log::warn!("no attachment for moment {}", id);
+                        caps[0].to_string()
+                    }
                 }
+            })
+            .to_string()
+    }
 
-                let out = pandoc.wait_with_output().expect("Failed to read stdout");
-                let panbody = String::from_utf8_lossy(&out.stdout).to_string();
-                let mut body = panbody.lines().skip(4).collect::<Vec<&str>>().join("\n");
-                if let Some(photos) = photos {
-                    let mut photos: Vec<Photo> = photos.to_vec();
-                    photos.sort_by_key(|p| p.order_in_entry);
-                    for photo in photos {
-                        body = PHOTO_REGEX
-                            .replace(&body, photo.link().as_str())
-                            .to_string();
+    /// Convert a Markdown string to Org, shifting ATX headings down by
+    /// `shift` levels. `dayone-moment://` image placeholders are passed
+    /// through as bare `[[…]]` links so the photo substitution can rewrite
+    /// them afterward.
+    fn markdown_to_org(text: &str, shift: usize) -> String {
+        let mut out = String::new();
+        let mut in_image = false;
+        // Stack of ordered-list counters; `None` marks an unordered list.
+        let mut lists: Vec<Option<u64>> = Vec::new();
+
+        for event in Parser::new(text) {
+            match event {
+                Event::Start(Tag::Heading(level)) => {
+                    let stars = "*".repeat(level as usize + shift);
+                    out.push_str(&stars);
+                    out.push(' ');
+                }
+                Event::End(Tag::Heading(_)) => out.push('\n'),
+                Event::Start(Tag::Paragraph) => {}
+                Event::End(Tag::Paragraph) => out.push_str("\n\n"),
+                Event::Start(Tag::Strong) | Event::End(Tag::Strong) => out.push('*'),
+                Event::Start(Tag::Emphasis) | Event::End(Tag::Emphasis) => out.push('/'),
+                Event::Start(Tag::Link(_, url, _)) => {
+                    out.push_str(&format!("[[{}][", url));
+                }
+                Event::End(Tag::Link(..)) => out.push_str("]]"),
+                Event::Start(Tag::Image(_, url, _)) => {
+                    out.push_str(&format!("[[{}]]", url));
+                    in_image = true;
+                }
+                Event::End(Tag::Image(..)) => in_image = false,
+                Event::Start(Tag::CodeBlock(kind)) => {
+                    let lang = match kind {
+                        CodeBlockKind::Fenced(lang) => lang.to_string(),
+                        CodeBlockKind::Indented => String::new(),
+                    };
+                    out.push_str(format!("#+begin_src {}", lang).trim_end());
+                    out.push('\n');
+                }
+                Event::End(Tag::CodeBlock(_)) => {
+                    out.push_str("#+end_src\n");
+                }
+                Event::Start(Tag::List(start)) => lists.push(start),
+                Event::End(Tag::List(_)) => {
+                    lists.pop();
+                }
+                Event::Start(Tag::Item) => {
+                    let depth = lists.len().saturating_sub(1);
+                    out.push_str(&"  ".repeat(depth));
+                    match lists.last_mut() {
+                        Some(Some(n)) => {
+                            out.push_str(&format!("{}. ", n));
+                            *n += 1;
+                        }
+                        _ => out.push_str("- "),
                     }
                 }
-                return Some(body);
+                Event::End(Tag::Item) => out.push('\n'),
+                Event::Text(s) => {
+                    if !in_image {
+                        out.push_str(&s);
+                    }
+                }
+                Event::Code(s) => out.push_str(&format!("~{}~", s)),
+                Event::SoftBreak | Event::HardBreak => out.push('\n'),
+                _ => {}
             }
-            None
         }
+
+        out.trim_end().to_string()
     }
 
     #[derive(Deserialize)]
@@ -198,9 +383,136 @@ pub mod day_one {
     }
 }
 
+pub mod media {
+    use crate::day_one::{MediaKind, Photo};
+    use std::fs;
+    use std::path::Path;
+
+    /// Copy a referenced attachment from the Day One export into the output
+    /// tree, verifying that its contents hash to the recorded `md5`.
+    pub fn copy(
+        export_root: &Path,
+        out_root: &Path,
+        kind: MediaKind,
+        moment: &Photo,
+    ) -> std::io::Result<()> {
+        let src = export_root.join(kind.source_dir()).join(moment.file_name());
+        if !src.exists() {
+            log::warn!("missing {} file {}", kind.source_dir(), src.display());
+            return Ok(());
+        }
+        let bytes = fs::read(&src)?;
+        let digest = format!("{:x}", md5::compute(&bytes));
+        if digest != moment.md5 {
+            log::warn!(
+                "md5 mismatch for {}: expected {}, got {}",
+                src.display(),
+                moment.md5,
+                digest
+            );
+        }
+        let dir = out_root.join(kind.output_dir());
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(moment.file_name()), &bytes)?;
+        Ok(())
+    }
+}
+
+pub mod geocode {
+    use serde::Deserialize;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+
+    #[derive(Deserialize)]
+    struct Reverse {
+        display_name: Option<String>,
+    }
+
+    /// Reverse geocoder backed by a disk cache keyed on rounded coordinates,
+    /// so re-runs over the same export stay offline and fast.
+    pub struct Geocoder {
+        path: PathBuf,
+        cache: RefCell<HashMap<String, Option<String>>>,
+    }
+
+    impl Geocoder {
+        /// Load a geocoder, reading any previously cached responses.
+        pub fn load(path: &Path) -> Geocoder {
+            let cache = std::fs::read(path)
+                .ok()
+                .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+                .unwrap_or_default();
+            Geocoder {
+                path: path.to_path_buf(),
+                cache: RefCell::new(cache),
+            }
+        }
+
+        fn key(lat: f32, long: f32) -> String {
+            format!("{:.3},{:.3}", lat, long)
+        }
+
+        /// Resolve a place name for the coordinate, hitting the network only
+        /// on a cache miss.
+        pub fn reverse(&self, lat: f32, long: f32) -> Option<String> {
+            let key = Self::key(lat, long);
+            if let Some(cached) = self.cache.borrow().get(&key) {
+                return cached.clone();
+            }
+            let url = format!(
+                "https://nominatim.openstreetmap.org/reverse?format=json&lat={}&lon={}",
+                lat, long
+            );
+            let name = fetch_json::<Reverse>(&url).and_then(|r| r.display_name);
+            self.cache.borrow_mut().insert(key, name.clone());
+            name
+        }
+
+        /// Persist the cache to disk.
+        pub fn save(&self) -> std::io::Result<()> {
+            let json = serde_json::to_vec_pretty(&*self.cache.borrow())
+                .expect("couldn't serialize geocode cache");
+            std::fs::write(&self.path, json)
+        }
+    }
+
+    fn fetch_json<T: serde::de::DeserializeOwned>(url: &str) -> Option<T> {
+        let body = ureq::get(url)
+            .set("User-Agent", "do2org")
+            .call()
+            .ok()?
+            .into_string()
+            .ok()?;
+        serde_json::from_str(&body).ok()
+    }
+}
+
 pub mod time_tree {
     use chrono::prelude::*;
     use std::collections::HashMap;
+    use std::collections::BTreeMap;
+    use std::fmt::Write as _;
+    use std::fs;
+    use std::path::Path;
+
+    /// How `write_to_dir` buckets entries onto disk.
+    #[derive(clap::ValueEnum, Clone, Copy, Debug)]
+    pub enum Split {
+        Year,
+        Month,
+    }
+
+    /// How the tree is rendered to stdout.
+    #[derive(clap::ValueEnum, Clone, Copy, Debug)]
+    pub enum Format {
+        /// The full verbose Org time tree.
+        Org,
+        /// The normalized tree re-serialized as JSON.
+        Json,
+        /// A compact per-day summary table.
+        Table,
+    }
 
     struct Day {
         entries: Vec<crate::day_one::Entry>,
@@ -271,48 +583,248 @@ pub mod time_tree {
             journal
         }
 
-        pub fn print(&self) {
+        fn render_day(
+            buf: &mut String,
+            y: &i32,
+            m: &u32,
+            d: &u32,
+            day: &Day,
+            geocoder: Option<&crate::geocode::Geocoder>,
+        ) {
+            let _ = writeln!(buf, "*** {}-{}-{} {}", y, m, d, Day::name_from(y, m, d));
+            for entry in &day.entries {
+                log::debug!("converting entry {}", entry.creation_date);
+                let links = entry.moment_links();
+                let _ = writeln!(
+                    buf,
+                    "**** {}",
+                    entry.title(&links).unwrap_or("Empty".to_string())
+                );
+                let _ = writeln!(buf, ":PROPERTIES:");
+                let mut props: Vec<_> = entry.properties(geocoder).into_iter().collect();
+                props.sort();
+                for (prop, value) in props {
+                    let _ = writeln!(buf, ":{}: {}", prop, value);
+                }
+                let _ = writeln!(buf, ":END:");
+                let _ = writeln!(buf, "{}", entry.body(&links).unwrap_or("".to_string()));
+            }
+        }
+
+        fn render_month(
+            buf: &mut String,
+            y: &i32,
+            m: &u32,
+            month: &Month,
+            geocoder: Option<&crate::geocode::Geocoder>,
+        ) {
+            let _ = writeln!(buf, "** {}-{} {}", y, m, Month::name_from(m));
+            let mut day_nums: Vec<_> = month.days.keys().collect();
+            day_nums.sort();
+            for d in day_nums {
+                let day = month.days.get(d).unwrap();
+                Self::render_day(buf, y, m, d, day, geocoder);
+            }
+        }
+
+        fn render_year(
+            buf: &mut String,
+            y: &i32,
+            year: &Year,
+            geocoder: Option<&crate::geocode::Geocoder>,
+        ) {
+            let _ = writeln!(buf, "* {}", y);
+            let mut month_nums: Vec<_> = year.months.keys().collect();
+            month_nums.sort();
+            for m in month_nums {
+                let month = year.months.get(m).unwrap();
+                Self::render_month(buf, y, m, month, geocoder);
+            }
+        }
+
+        pub fn print(&self, geocoder: Option<&crate::geocode::Geocoder>) {
+            let mut year_nums: Vec<_> = self.years.keys().collect();
+            year_nums.sort();
+            for y in year_nums {
+                let year = self.years.get(y).unwrap();
+                let mut buf = String::new();
+                Self::render_year(&mut buf, y, year, geocoder);
+                print!("{}", buf);
+            }
+        }
+
+        /// Re-serialize the normalized tree to JSON, keyed by year / month /
+        /// day so the output is sorted and stable.
+        pub fn print_json(&self) {
+            let mut tree: BTreeMap<String, BTreeMap<String, BTreeMap<String, &Vec<crate::day_one::Entry>>>> =
+                BTreeMap::new();
+            for (y, year) in &self.years {
+                for (m, month) in &year.months {
+                    for (d, day) in &month.days {
+                        tree.entry(format!("{}", y))
+                            .or_default()
+                            .entry(format!("{:02}", m))
+                            .or_default()
+                            .insert(format!("{:02}", d), &day.entries);
+                    }
+                }
+            }
+            let json = serde_json::to_string_pretty(&tree).expect("couldn't serialize tree");
+            println!("{}", json);
+        }
+
+        /// Print a compact per-day summary: date, weekday, entry count and
+        /// whether photos, a location or weather are present that day.
+        pub fn print_table(&self) {
+            println!(
+                "{:<12} {:<10} {:>7} {:>6} {:>8} {:>7}",
+                "Date", "Weekday", "Entries", "Photos", "Location", "Weather"
+            );
             let mut year_nums: Vec<_> = self.years.keys().collect();
             year_nums.sort();
             for y in year_nums {
                 let year = self.years.get(y).unwrap();
                 let mut month_nums: Vec<_> = year.months.keys().collect();
-                println!("* {}", y);
                 month_nums.sort();
                 for m in month_nums {
                     let month = year.months.get(m).unwrap();
                     let mut day_nums: Vec<_> = month.days.keys().collect();
                     day_nums.sort();
-                    println!("** {}-{} {}", y, m, Month::name_from(m));
                     for d in day_nums {
                         let day = month.days.get(d).unwrap();
-                        println!("*** {}-{}-{} {}", y, m, d, Day::name_from(y, m, d));
+                        let yes_no = |p: bool| if p { "yes" } else { "-" };
+                        let photos = day
+                            .entries
+                            .iter()
+                            .any(|e| e.photos.as_ref().map_or(false, |p| !p.is_empty()));
+                        let location = day.entries.iter().any(|e| e.location.is_some());
+                        let weather = day.entries.iter().any(|e| e.weather.is_some());
+                        println!(
+                            "{:<12} {:<10} {:>7} {:>6} {:>8} {:>7}",
+                            format!("{}-{:02}-{:02}", y, m, d),
+                            Day::name_from(y, m, d),
+                            day.entries.len(),
+                            yes_no(photos),
+                            yes_no(location),
+                            yes_no(weather),
+                        );
+                    }
+                }
+            }
+        }
+
+        /// Write the tree to `root`, one `.org` file per year (or per month
+        /// when `split` is [`Split::Month`]). Output is sorted so regenerating
+        /// over the same export is idempotent.
+        pub fn write_to_dir(
+            &self,
+            root: &Path,
+            split: Split,
+            geocoder: Option<&crate::geocode::Geocoder>,
+        ) -> std::io::Result<()> {
+            fs::create_dir_all(root)?;
+            let mut year_nums: Vec<_> = self.years.keys().collect();
+            year_nums.sort();
+            for y in year_nums {
+                let year = self.years.get(y).unwrap();
+                match split {
+                    Split::Year => {
+                        let mut buf = format!("#+TITLE: {}\n", y);
+                        Self::render_year(&mut buf, y, year, geocoder);
+                        let path = root.join(format!("{}.org", y));
+                        log::debug!("writing {}", path.display());
+                        fs::write(path, buf)?;
+                    }
+                    Split::Month => {
+                        let mut month_nums: Vec<_> = year.months.keys().collect();
+                        month_nums.sort();
+                        for m in month_nums {
+                            let month = year.months.get(m).unwrap();
+                            let mut buf =
+                                format!("#+TITLE: {}-{} {}\n* {}\n", y, m, Month::name_from(m), y);
+                            Self::render_month(&mut buf, y, m, month, geocoder);
+                            let path = root.join(format!("{}-{:02}.org", y, m));
+                            log::debug!("writing {}", path.display());
+                            fs::write(path, buf)?;
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        /// Copy and verify every referenced attachment from the Day One
+        /// export at `export_root` into the output tree at `out_root`.
+        pub fn copy_media(&self, export_root: &Path, out_root: &Path) -> std::io::Result<()> {
+            for year in self.years.values() {
+                for month in year.months.values() {
+                    for day in month.days.values() {
                         for entry in &day.entries {
-                            let first_photo = match &entry.photos {
-                                Some(photos) => Some(photos[0].link()),
-                                None => None,
-                            };
-                            println!(
-                                "**** {}",
-                                entry.title(first_photo).unwrap_or("Empty".to_string())
-                            );
-                            println!(":PROPERTIES:");
-                            for (prop, value) in &entry.properties() {
-                                println!(":{}: {}", prop, value);
+                            for (kind, moment) in entry.moments() {
+                                crate::media::copy(export_root, out_root, kind, moment)?;
                             }
-                            println!(":END:");
-                            println!("{}", entry.body(&entry.photos).unwrap_or("".to_string()));
                         }
                     }
                 }
             }
+            Ok(())
         }
     }
 }
 
 fn main() {
-    let reader = fs::read("./Journal.json").expect("where is Journal.json?");
+    let args = Args::parse();
+
+    let level = if args.verbose {
+        log::LevelFilter::Debug
+    } else {
+        log::LevelFilter::Info
+    };
+    env_logger::Builder::new()
+        .filter_level(level)
+        .format_timestamp(None)
+        .init();
+
+    log::debug!("reading {}", args.input.display());
+    let reader = fs::read(&args.input).expect("where is Journal.json?");
     let json: day_one::Journal = serde_json::from_slice(&reader).expect("couldn't unwrap");
+    let entry_count = json.entries.len();
     let journal = time_tree::Root::from(json);
-    journal.print()
+
+    if args.dry_run {
+        log::info!("dry run: parsed {} entries, writing nothing", entry_count);
+        return;
+    }
+
+    let geocoder = if args.geocode {
+        Some(geocode::Geocoder::load(std::path::Path::new(
+            ".do2org-geocode-cache.json",
+        )))
+    } else {
+        None
+    };
+
+    log::info!("converting {} entries", entry_count);
+    match &args.output {
+        Some(dir) => {
+            journal
+                .write_to_dir(dir, args.split, geocoder.as_ref())
+                .expect("couldn't write output");
+            let export_root = args.input.parent().unwrap_or_else(|| std::path::Path::new("."));
+            journal
+                .copy_media(export_root, dir)
+                .expect("couldn't copy media");
+        }
+        None => match args.format {
+            time_tree::Format::Org => journal.print(geocoder.as_ref()),
+            time_tree::Format::Json => journal.print_json(),
+            time_tree::Format::Table => journal.print_table(),
+        },
+    }
+
+    if let Some(geocoder) = &geocoder {
+        if let Err(err) = geocoder.save() {
+            log::warn!("couldn't save geocode cache: {}", err);
+        }
+    }
 }